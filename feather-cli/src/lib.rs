@@ -1,41 +1,302 @@
-use std::ffi::{c_void, c_char};
+use std::ffi::{c_void, c_char, CStr, CString};
+use std::marker::PhantomData;
 use std::path::Path;
 
+use ndarray::ArrayView2;
+use thiserror::Error;
+
+#[repr(C)]
+struct FeatherDB(c_void);
+
 #[repr(C)]
-pub struct DB(*mut c_void);  // ← `pub`
+struct FeatherIterRaw(c_void);
+
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeatherStatus {
+    Ok = 0,
+    DimensionMismatch = 1,
+    Io = 2,
+    NotFound = 3,
+    Corrupt = 4,
+    Internal = 5,
+    ReadOnly = 6,
+}
+
+/// Whether a `DB` may be mutated. Mirrors `FeatherMode` in `feather.h`.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    ReadOnly = 0,
+    ReadWrite = 1,
+}
+
+/// On-disk vector encoding. Mirrors `FeatherQuantMode` in `feather.h`.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantMode {
+    None = 0,
+    Int8 = 1,
+}
+
+/// Errors that can cross the `feather_core` FFI boundary.
+#[derive(Debug, Error)]
+pub enum FeatherError {
+    #[error("dimension mismatch: {0}")]
+    DimensionMismatch(String),
+    #[error("I/O error: {0}")]
+    Io(String),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("corrupt database: {0}")]
+    Corrupt(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+    #[error("database is read-only: {0}")]
+    ReadOnly(String),
+}
+
+impl FeatherStatus {
+    fn from_raw(raw: i32) -> Self {
+        match raw {
+            0 => FeatherStatus::Ok,
+            1 => FeatherStatus::DimensionMismatch,
+            2 => FeatherStatus::Io,
+            3 => FeatherStatus::NotFound,
+            4 => FeatherStatus::Corrupt,
+            6 => FeatherStatus::ReadOnly,
+            _ => FeatherStatus::Internal,
+        }
+    }
+}
 
 extern "C" {
-    fn feather_open(path: *const c_char, dim: usize) -> *mut c_void;
-    fn feather_add(db: *mut c_void, id: u64, vec: *const f32, len: usize);
-    fn feather_search(db: *mut c_void, query: *const f32, len: usize, k: usize,
-                      out_ids: *mut u64, out_dists: *mut f32);
-    fn feather_save(db: *mut c_void);
-    fn feather_close(db: *mut c_void);
+    fn feather_open(path: *const c_char, dim: usize, mode: i32, quant: i32, out_db: *mut *mut FeatherDB, out_err: *mut *mut c_char) -> i32;
+    fn feather_add(db: *mut FeatherDB, id: u64, vec: *const f32, len: usize, out_err: *mut *mut c_char) -> i32;
+    fn feather_add_batch(db: *mut FeatherDB, ids: *const u64, vecs: *const f32, n: usize, dim: usize, out_err: *mut *mut c_char) -> i32;
+    fn feather_add_with_meta(db: *mut FeatherDB, id: u64, vec: *const f32, len: usize, json: *const c_char, out_err: *mut *mut c_char) -> i32;
+    fn feather_search_filtered(db: *mut FeatherDB, query: *const f32, len: usize, k: usize, filter: *const c_char,
+                               out_ids: *mut u64, out_dists: *mut f32, out_err: *mut *mut c_char) -> i32;
+    fn feather_search(db: *mut FeatherDB, query: *const f32, len: usize, k: usize,
+                      out_ids: *mut u64, out_dists: *mut f32, out_err: *mut *mut c_char) -> i32;
+    fn feather_save(db: *mut FeatherDB, out_err: *mut *mut c_char) -> i32;
+    fn feather_close(db: *mut FeatherDB);
+    fn feather_remove(db: *mut FeatherDB, id: u64, out_removed: *mut i32, out_err: *mut *mut c_char) -> i32;
+    fn feather_compact(db: *mut FeatherDB, out_err: *mut *mut c_char) -> i32;
+    fn feather_iter_new(db: *mut FeatherDB) -> *mut FeatherIterRaw;
+    fn feather_iter_next(it: *mut FeatherIterRaw, out_id: *mut u64, out_vec: *mut f32) -> i32;
+    fn feather_iter_free(it: *mut FeatherIterRaw);
+    fn feather_free_error(err: *mut c_char);
+}
+
+/// Turns a non-OK status plus an out-param error message into a `FeatherError`.
+fn status_to_error(status: i32, out_err: *mut c_char) -> FeatherError {
+    let message = unsafe {
+        if out_err.is_null() {
+            String::from("no error message provided")
+        } else {
+            let msg = CStr::from_ptr(out_err).to_string_lossy().into_owned();
+            feather_free_error(out_err);
+            msg
+        }
+    };
+    match FeatherStatus::from_raw(status) {
+        FeatherStatus::DimensionMismatch => FeatherError::DimensionMismatch(message),
+        FeatherStatus::Io => FeatherError::Io(message),
+        FeatherStatus::NotFound => FeatherError::NotFound(message),
+        FeatherStatus::Corrupt => FeatherError::Corrupt(message),
+        FeatherStatus::ReadOnly => FeatherError::ReadOnly(message),
+        FeatherStatus::Internal | FeatherStatus::Ok => FeatherError::Internal(message),
+    }
+}
+
+/// A handle to a feather database. The underlying core guards all access
+/// with a reader-writer lock, so `DB` can be wrapped in an `Arc` and shared
+/// across threads (see the `Send`/`Sync` impls below).
+pub struct DB {
+    ptr: *mut FeatherDB,
+    dim: usize,
 }
 
 impl DB {
-    pub fn open(path: &Path, dim: usize) -> Option<Self> {
-        let c_path = std::ffi::CString::new(path.to_str()?).ok()?;
-        let ptr = unsafe { feather_open(c_path.as_ptr(), dim) };
-        if ptr.is_null() { None } else { Some(DB(ptr)) }
+    pub fn open(path: &Path, dim: usize, mode: Mode) -> Result<Self, FeatherError> {
+        Self::open_raw(path, dim, mode, QuantMode::None)
+    }
+
+    /// Opens (or creates) `path`, storing each vector component as one
+    /// quantized byte on disk instead of 4 bytes of f32. Only takes effect
+    /// when `path` doesn't already exist; an existing file keeps whatever
+    /// quantization it was saved with.
+    pub fn open_quantized(path: &Path, dim: usize, quant: QuantMode) -> Result<Self, FeatherError> {
+        Self::open_raw(path, dim, Mode::ReadWrite, quant)
+    }
+
+    fn open_raw(path: &Path, dim: usize, mode: Mode, quant: QuantMode) -> Result<Self, FeatherError> {
+        let c_path = CString::new(path.to_str().ok_or_else(|| {
+            FeatherError::Internal(format!("path {:?} is not valid UTF-8", path))
+        })?)
+        .map_err(|e| FeatherError::Internal(e.to_string()))?;
+
+        let mut db_ptr: *mut FeatherDB = std::ptr::null_mut();
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe {
+            feather_open(c_path.as_ptr(), dim, mode as i32, quant as i32, &mut db_ptr, &mut err)
+        };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(DB { ptr: db_ptr, dim })
+    }
+
+    pub fn add(&self, id: u64, vec: &[f32]) -> Result<(), FeatherError> {
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe { feather_add(self.ptr, id, vec.as_ptr(), vec.len(), &mut err) };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(())
     }
 
-    pub fn add(&self, id: u64, vec: &[f32]) {
-        unsafe { feather_add(self.0, id, vec.as_ptr(), vec.len()) }
+    /// Adds every row of `vecs` under a single call, committing the whole
+    /// batch in one shot instead of paying per-vector FFI overhead.
+    pub fn add_batch(&self, ids: &[u64], vecs: &ArrayView2<f32>) -> Result<(), FeatherError> {
+        let (n, dim) = vecs.dim();
+        if ids.len() != n {
+            return Err(FeatherError::Internal(format!(
+                "ids has {} entries but vecs has {} rows", ids.len(), n
+            )));
+        }
+        let vecs = vecs.as_standard_layout();
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe {
+            feather_add_batch(self.ptr, ids.as_ptr(), vecs.as_ptr(), n, dim, &mut err)
+        };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(())
     }
 
-    pub fn search(&self, query: &[f32], k: usize) -> (Vec<u64>, Vec<f32>) {
+    /// Adds (or overwrites) `id` along with a JSON metadata payload that
+    /// `search_filtered` can later predicate on.
+    pub fn add_with_meta(&self, id: u64, vec: &[f32], meta: &serde_json::Value) -> Result<(), FeatherError> {
+        let json = CString::new(meta.to_string())
+            .map_err(|e| FeatherError::Internal(e.to_string()))?;
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe {
+            feather_add_with_meta(self.ptr, id, vec.as_ptr(), vec.len(), json.as_ptr(), &mut err)
+        };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(())
+    }
+
+    pub fn search(&self, query: &[f32], k: usize) -> Result<(Vec<u64>, Vec<f32>), FeatherError> {
         let mut ids = vec![0u64; k];
         let mut dists = vec![0f32; k];
-        unsafe {
-            feather_search(self.0, query.as_ptr(), query.len(), k, ids.as_mut_ptr(), dists.as_mut_ptr())
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe {
+            feather_search(self.ptr, query.as_ptr(), query.len(), k, ids.as_mut_ptr(), dists.as_mut_ptr(), &mut err)
         };
-        (ids, dists)
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok((ids, dists))
+    }
+
+    /// Like `search`, but only considers ids whose metadata payload
+    /// satisfies `filter` (e.g. `"age > 30 AND tag = \"vip\""`).
+    pub fn search_filtered(&self, query: &[f32], k: usize, filter: &str) -> Result<(Vec<u64>, Vec<f32>), FeatherError> {
+        let mut ids = vec![0u64; k];
+        let mut dists = vec![0f32; k];
+        let c_filter = CString::new(filter).map_err(|e| FeatherError::Internal(e.to_string()))?;
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe {
+            feather_search_filtered(self.ptr, query.as_ptr(), query.len(), k, c_filter.as_ptr(),
+                                     ids.as_mut_ptr(), dists.as_mut_ptr(), &mut err)
+        };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok((ids, dists))
+    }
+
+    pub fn save(&self) -> Result<(), FeatherError> {
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe { feather_save(self.ptr, &mut err) };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(())
+    }
+
+    /// Iterates over every `(id, vector)` pair currently stored, e.g. to
+    /// export a backup or re-import into a database with a different `dim`
+    /// or quantization mode. Backed by a snapshot of the ids present at the
+    /// time of the call; ids added afterwards aren't yielded, while ids
+    /// removed afterwards are skipped.
+    pub fn iter(&self) -> FeatherIter<'_> {
+        let ptr = unsafe { feather_iter_new(self.ptr) };
+        FeatherIter { ptr, dim: self.dim, _db: PhantomData }
+    }
+
+    /// Tombstones `id`, hiding it from `search`/`iter` immediately; its
+    /// storage isn't reclaimed until `compact` runs. Returns whether `id`
+    /// was present (and not already removed).
+    pub fn remove(&self, id: u64) -> Result<bool, FeatherError> {
+        let mut removed: i32 = 0;
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe { feather_remove(self.ptr, id, &mut removed, &mut err) };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(removed != 0)
     }
 
-    pub fn save(&self) { unsafe { feather_save(self.0) } }
+    /// Physically erases every tombstoned id and persists the result.
+    pub fn compact(&self) -> Result<(), FeatherError> {
+        let mut err: *mut c_char = std::ptr::null_mut();
+        let status = unsafe { feather_compact(self.ptr, &mut err) };
+        if status != FeatherStatus::Ok as i32 {
+            return Err(status_to_error(status, err));
+        }
+        Ok(())
+    }
 }
 
 impl Drop for DB {
-    fn drop(&mut self) { unsafe { feather_close(self.0) } }
+    fn drop(&mut self) { unsafe { feather_close(self.ptr) } }
+}
+
+// SAFETY: `feather_core` serializes every access to a `FeatherDB` behind an
+// internal `std::shared_mutex` (shared for reads, exclusive for writes), so
+// the raw pointer can be handed across threads and shared concurrently.
+unsafe impl Send for DB {}
+unsafe impl Sync for DB {}
+
+/// Returned by `DB::iter`. Borrows the `DB` for its lifetime since the
+/// underlying snapshot reads through the same FFI handle.
+pub struct FeatherIter<'a> {
+    ptr: *mut FeatherIterRaw,
+    dim: usize,
+    _db: PhantomData<&'a DB>,
+}
+
+impl<'a> Iterator for FeatherIter<'a> {
+    type Item = (u64, Vec<f32>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut id = 0u64;
+        let mut vec = vec![0f32; self.dim];
+        let found = unsafe { feather_iter_next(self.ptr, &mut id, vec.as_mut_ptr()) };
+        if found == 0 {
+            return None;
+        }
+        Some((id, vec))
+    }
+}
+
+impl<'a> Drop for FeatherIter<'a> {
+    fn drop(&mut self) { unsafe { feather_iter_free(self.ptr) } }
 }
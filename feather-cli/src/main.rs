@@ -1,7 +1,7 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
-use feather_cli::DB;
-use ndarray::Array1;
+use feather_cli::{DB, Mode, QuantMode};
+use ndarray::{Array1, Array2};
 
 #[derive(Parser)]
 #[command(name = "feather")]
@@ -12,35 +12,125 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    New { path: PathBuf, #[arg(long)] dim: usize },
-    Add { db: PathBuf, id: u64, #[arg(short)] npy: PathBuf },
-    Search { db: PathBuf, #[arg(short)] npy: PathBuf, #[arg(long, default_value_t = 5)] k: usize },
+    New { path: PathBuf, #[arg(long)] dim: usize, #[arg(long)] quantize: bool },
+    Add { db: PathBuf, id: u64, #[arg(short)] npy: PathBuf, #[arg(long)] meta: Option<String> },
+    /// `meta`, if given, is a path to a JSON file holding an array of one
+    /// flat object per row, parallel to `ids`.
+    AddBatch { db: PathBuf, #[arg(short)] npy: PathBuf, #[arg(long)] ids: Option<PathBuf>, #[arg(long)] meta: Option<PathBuf> },
+    Search { db: PathBuf, #[arg(short)] npy: PathBuf, #[arg(long, default_value_t = 5)] k: usize, #[arg(long)] filter: Option<String> },
+    Dump { db: PathBuf, out: PathBuf, #[arg(long)] dim: usize },
+    Delete { db: PathBuf, id: u64, #[arg(long)] dim: usize },
+    Compact { db: PathBuf, #[arg(long)] dim: usize },
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
     match cli.command {
-        Commands::New { path, dim } => {
-            DB::open(&path, dim).ok_or_else(|| anyhow::anyhow!("Failed to create DB"))?;
+        Commands::New { path, dim, quantize } => {
+            if quantize {
+                DB::open_quantized(&path, dim, QuantMode::Int8)
+                    .map_err(|e| anyhow::anyhow!("failed to create {:?}: {}", path, e))?;
+            } else {
+                DB::open(&path, dim, Mode::ReadWrite)
+                    .map_err(|e| anyhow::anyhow!("failed to create {:?}: {}", path, e))?;
+            }
             println!("Created: {:?}", path);
         }
-        Commands::Add { db, id, npy } => {
+        Commands::Add { db, id, npy, meta } => {
             let arr: Array1<f32> = ndarray_npy::read_npy(&npy)?;
             let dim = arr.len();
-            let db = DB::open(&db, dim).ok_or_else(|| anyhow::anyhow!("Open failed"))?;
-            db.add(id, arr.as_slice().unwrap());
-            db.save();
+            let db = DB::open(&db, dim, Mode::ReadWrite).map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+            match meta {
+                Some(json) => {
+                    let meta: serde_json::Value = serde_json::from_str(&json)
+                        .map_err(|e| anyhow::anyhow!("invalid --meta JSON: {}", e))?;
+                    db.add_with_meta(id, arr.as_slice().unwrap(), &meta)
+                        .map_err(|e| anyhow::anyhow!("failed to add ID {}: {}", id, e))?;
+                }
+                None => {
+                    db.add(id, arr.as_slice().unwrap())
+                        .map_err(|e| anyhow::anyhow!("failed to add ID {}: {}", id, e))?;
+                }
+            }
+            db.save().map_err(|e| anyhow::anyhow!("failed to save database: {}", e))?;
             println!("Added ID {}", id);
         }
-        Commands::Search { db, npy, k } => {
+        Commands::AddBatch { db, npy, ids, meta } => {
+            let arr: Array2<f32> = ndarray_npy::read_npy(&npy)?;
+            let (n, dim) = arr.dim();
+            let ids: Vec<u64> = match ids {
+                Some(path) => ndarray_npy::read_npy::<_, Array1<u64>>(&path)?.to_vec(),
+                None => (0..n as u64).collect(),
+            };
+            if ids.len() != n {
+                anyhow::bail!("ids file has {} entries but npy has {} rows", ids.len(), n);
+            }
+            let db = DB::open(&db, dim, Mode::ReadWrite).map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+            match meta {
+                Some(path) => {
+                    let metas: Vec<serde_json::Value> =
+                        serde_json::from_str(&std::fs::read_to_string(&path)?)
+                            .map_err(|e| anyhow::anyhow!("invalid --meta JSON in {:?}: {}", path, e))?;
+                    if metas.len() != n {
+                        anyhow::bail!("meta file has {} entries but npy has {} rows", metas.len(), n);
+                    }
+                    for ((id, row), meta) in ids.iter().zip(arr.outer_iter()).zip(metas.iter()) {
+                        db.add_with_meta(*id, row.as_slice().unwrap(), meta)
+                            .map_err(|e| anyhow::anyhow!("failed to add ID {}: {}", id, e))?;
+                    }
+                }
+                None => {
+                    db.add_batch(&ids, &arr.view())
+                        .map_err(|e| anyhow::anyhow!("batch add failed: {}", e))?;
+                }
+            }
+            db.save().map_err(|e| anyhow::anyhow!("failed to save database: {}", e))?;
+            println!("Added {} vectors", n);
+        }
+        Commands::Search { db, npy, k, filter } => {
             let arr: Array1<f32> = ndarray_npy::read_npy(&npy)?;
             let dim = arr.len();
-            let db = DB::open(&db, dim).ok_or_else(|| anyhow::anyhow!("Open failed"))?;
-            let (ids, dists) = db.search(arr.as_slice().unwrap(), k);
+            let db = DB::open(&db, dim, Mode::ReadOnly).map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+            let (ids, dists) = match filter {
+                Some(filter) => db.search_filtered(arr.as_slice().unwrap(), k, &filter)
+                    .map_err(|e| anyhow::anyhow!("search failed: {}", e))?,
+                None => db.search(arr.as_slice().unwrap(), k)
+                    .map_err(|e| anyhow::anyhow!("search failed: {}", e))?,
+            };
             for (id, dist) in ids.iter().zip(dists.iter()) {
                 println!("ID: {}  dist: {:.4}", id, dist);
             }
         }
+        Commands::Dump { db, out, dim } => {
+            let db = DB::open(&db, dim, Mode::ReadOnly).map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+            let mut ids = Vec::new();
+            let mut flat = Vec::new();
+            for (id, vec) in db.iter() {
+                ids.push(id);
+                flat.extend(vec);
+            }
+            let n = ids.len();
+            let arr = Array2::from_shape_vec((n, dim), flat)?;
+            ndarray_npy::write_npy(&out, &arr)?;
+            let ids_path = out.with_extension("ids.npy");
+            ndarray_npy::write_npy(&ids_path, &Array1::from(ids))?;
+            println!("Dumped {} vectors to {:?} (ids: {:?})", n, out, ids_path);
+        }
+        Commands::Delete { db, id, dim } => {
+            let db = DB::open(&db, dim, Mode::ReadWrite).map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+            let removed = db.remove(id).map_err(|e| anyhow::anyhow!("failed to remove ID {}: {}", id, e))?;
+            db.save().map_err(|e| anyhow::anyhow!("failed to save database: {}", e))?;
+            if removed {
+                println!("Removed ID {}", id);
+            } else {
+                println!("ID {} was not present", id);
+            }
+        }
+        Commands::Compact { db, dim } => {
+            let db = DB::open(&db, dim, Mode::ReadWrite).map_err(|e| anyhow::anyhow!("failed to open database: {}", e))?;
+            db.compact().map_err(|e| anyhow::anyhow!("failed to compact database: {}", e))?;
+            println!("Compacted database");
+        }
     }
     Ok(())
 }